@@ -1,19 +1,35 @@
 #[macro_use]
 extern crate serde;
-use candid::{Decode, Encode};
+mod repository;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
 use ic_cdk::api::time;
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
-use ic_stable_structures::{BoundedStorable, Cell, DefaultMemoryImpl, StableBTreeMap, Storable};
-use std::{borrow::Cow, cell::RefCell, error::Error};
+use ic_stable_structures::{Cell, DefaultMemoryImpl, StableBTreeMap};
+use repository::{MaxEncodedSize, Repository, StableRepo};
+use std::{cell::RefCell, collections::HashMap, error::Error, fmt::Write};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 type IdCell = Cell<u64, Memory>;
 
 // Custom error type
-#[derive(Debug)]
+#[derive(candid::CandidType, Debug, Serialize, Deserialize)]
 enum CustomError {
     NotFound(String),
     EmptyFields(String),
+    InvalidReference(String),
+    InvalidDate(String),
+}
+
+impl std::fmt::Display for CustomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CustomError::NotFound(msg) => write!(f, "{}", msg),
+            CustomError::EmptyFields(msg) => write!(f, "{}", msg),
+            CustomError::InvalidReference(msg) => write!(f, "{}", msg),
+            CustomError::InvalidDate(msg) => write!(f, "{}", msg),
+        }
+    }
 }
 
 impl Error for CustomError {
@@ -21,56 +37,36 @@ impl Error for CustomError {
         match self {
             CustomError::NotFound(msg) => msg,
             CustomError::EmptyFields(msg) => msg,
+            CustomError::InvalidReference(msg) => msg,
+            CustomError::InvalidDate(msg) => msg,
         }
     }
 }
 
-#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+#[derive(candid::CandidType, Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 struct Team {
     id: u64,
     name: String,
     manager: String,
-    stadium: String,
+    stadium: u64,
 }
 
-impl Storable for Team {
-    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
-
-    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
-    }
-}
-
-impl BoundedStorable for Team {
+impl MaxEncodedSize for Team {
     const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
 }
 
-#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+#[derive(candid::CandidType, Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 struct Coach {
     id: u64,
     name: String,
-    team: String,
+    team: u64,
 }
 
-impl Storable for Coach {
-    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
-
-    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
-    }
-}
-
-impl BoundedStorable for Coach {
+impl MaxEncodedSize for Coach {
     const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
 }
 
-#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+#[derive(candid::CandidType, Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 struct Stadium {
     id: u64,
     name: String,
@@ -78,50 +74,30 @@ struct Stadium {
     capacity: u32,
 }
 
-impl Storable for Stadium {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
-    }
-}
-
-impl BoundedStorable for Stadium {
+impl MaxEncodedSize for Stadium {
     const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
 }
 
-#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+#[derive(candid::CandidType, Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 struct Match {
     id: u64,
-    home_team: String,
-    away_team: String,
-    venue: String,
+    home_team: u64,
+    away_team: u64,
+    venue: u64,
     match_date: u64, // Unix timestamp
+    home_goals: Option<u32>,
+    away_goals: Option<u32>,
 }
 
-impl Storable for Match {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
-    }
-}
-
-impl BoundedStorable for Match {
+impl MaxEncodedSize for Match {
     const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
 }
 
 #[derive(candid::CandidType, Serialize, Deserialize)]
 struct TeamPayload {
     name: String,
     manager: String,
-    stadium: String,
+    stadium: u64,
 }
 
 impl Default for TeamPayload {
@@ -129,7 +105,7 @@ impl Default for TeamPayload {
         TeamPayload {
             name: String::default(),
             manager: String::default(),
-            stadium: String::default(),
+            stadium: 0,
         }
     }
 }
@@ -137,14 +113,14 @@ impl Default for TeamPayload {
 #[derive(candid::CandidType, Serialize, Deserialize)]
 struct CoachPayload {
     name: String,
-    team: String,
+    team: u64,
 }
 
 impl Default for CoachPayload {
     fn default() -> Self {
         CoachPayload {
             name: String::default(),
-            team: String::default(),
+            team: 0,
         }
     }
 }
@@ -166,25 +142,144 @@ impl Default for StadiumPayload {
     }
 }
 
+/// How to interpret the `match_date` string on a `MatchPayload`.
+#[derive(candid::CandidType, Clone, Default, Serialize, Deserialize)]
+enum Conversion {
+    /// `match_date` is a plain unix-seconds integer.
+    #[default]
+    Timestamp,
+    /// `match_date` is RFC3339/ISO-8601 (e.g. `2026-08-15T19:00:00Z`).
+    Rfc3339,
+    /// `match_date` is a caller-supplied `strftime`-style format (e.g.
+    /// `"%Y-%m-%d %H:%M:%S"`).
+    TimestampFmt(String),
+    /// `match_date` is a caller-supplied `strftime`-style format with an
+    /// explicit timezone offset (e.g. `"%Y-%m-%d %H:%M:%S %z"`).
+    TimestampTZFmt(String),
+}
+
 #[derive(candid::CandidType, Serialize, Deserialize)]
 struct MatchPayload {
-    home_team: String,
-    away_team: String,
-    venue: String,
-    match_date: u64,
+    home_team: u64,
+    away_team: u64,
+    venue: u64,
+    match_date: String,
+    date_conversion: Conversion,
 }
 
 impl Default for MatchPayload {
     fn default() -> Self {
         MatchPayload {
-            home_team: String::default(),
-            away_team: String::default(),
-            venue: String::default(),
-            match_date: 0,
+            home_team: 0,
+            away_team: 0,
+            venue: 0,
+            match_date: String::default(),
+            date_conversion: Conversion::default(),
         }
     }
 }
 
+/// Parses a caller-supplied match date string into the internal `u64`
+/// nanosecond timestamp used by `ic_cdk::api::time`, per `conversion`:
+/// a plain unix-seconds integer (`Timestamp`), an RFC3339/ISO-8601 string
+/// (`Rfc3339`), a caller-supplied `strftime` format (`TimestampFmt(fmt)`),
+/// or a timezone-aware `strftime` format (`TimestampTZFmt(fmt)`).
+fn parse_match_date(date: &str, conversion: &Conversion) -> Result<u64, CustomError> {
+    let seconds = match conversion {
+        Conversion::Timestamp => date.parse::<i64>().map_err(|_| {
+            CustomError::InvalidDate(format!("'{}' is not a valid unix timestamp", date))
+        })?,
+        Conversion::Rfc3339 => DateTime::parse_from_rfc3339(date)
+            .map_err(|_| {
+                CustomError::InvalidDate(format!("'{}' is not a valid RFC3339 date", date))
+            })?
+            .timestamp(),
+        Conversion::TimestampFmt(fmt) => NaiveDateTime::parse_from_str(date, fmt)
+            .map_err(|_| {
+                CustomError::InvalidDate(format!(
+                    "'{}' does not match the format '{}'",
+                    date, fmt
+                ))
+            })?
+            .and_utc()
+            .timestamp(),
+        Conversion::TimestampTZFmt(fmt) => DateTime::parse_from_str(date, fmt)
+            .map_err(|_| {
+                CustomError::InvalidDate(format!(
+                    "'{}' does not match the timezone-aware format '{}'",
+                    date, fmt
+                ))
+            })?
+            .timestamp(),
+    };
+
+    if seconds < 0 {
+        return Err(CustomError::InvalidDate(format!(
+            "'{}' resolves to a timestamp before the unix epoch",
+            date
+        )));
+    }
+
+    (seconds as u64).checked_mul(1_000_000_000).ok_or_else(|| {
+        CustomError::InvalidDate(format!(
+            "'{}' resolves to a timestamp too far in the future to represent",
+            date
+        ))
+    })
+}
+
+// --- Event sourcing: append-only operation log with periodic checkpoints ---
+//
+// Every mutation to the entity maps below is also recorded here as an
+// `Operation`, so the full history of the registry can be replayed or rolled
+// back. To avoid replaying the log from scratch on every query, a full
+// snapshot of all four maps is stored every `KEEP_STATE_EVERY` operations;
+// rebuilding state then only requires replaying the tail since the nearest
+// checkpoint.
+
+/// One durably logged mutation. Each variant mirrors an `add_*`/`update_*`/
+/// `delete_*` handler and carries enough data to replay it without touching
+/// live storage.
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+enum Operation {
+    AddTeam(Team),
+    UpdateTeam { id: u64, team: Team },
+    DeleteTeam(u64),
+    AddCoach(Coach),
+    UpdateCoach { id: u64, coach: Coach },
+    DeleteCoach(u64),
+    AddStadium(Stadium),
+    UpdateStadium { id: u64, stadium: Stadium },
+    DeleteStadium(u64),
+    AddMatch(Match),
+    UpdateMatch { id: u64, match_: Match },
+    DeleteMatch(u64),
+}
+
+impl MaxEncodedSize for Operation {
+    const MAX_SIZE: u32 = 4096;
+}
+
+/// A full point-in-time copy of all four entity maps, taken every
+/// `KEEP_STATE_EVERY` operations so `get_state_at`/`rollback_to` don't have
+/// to replay the entire operation log from the beginning.
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct Snapshot {
+    teams: Vec<Team>,
+    coaches: Vec<Coach>,
+    stadiums: Vec<Stadium>,
+    matches: Vec<Match>,
+}
+
+impl MaxEncodedSize for Snapshot {
+    // Snapshots hold every row in the registry, so they're sized generously
+    // compared to the individual entities above.
+    const MAX_SIZE: u32 = 1_048_576;
+}
+
+/// A checkpoint is taken every this-many logged operations.
+const KEEP_STATE_EVERY: u64 = 64;
+
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
         MemoryManager::init(DefaultMemoryImpl::default())
@@ -195,206 +290,629 @@ thread_local! {
             .expect("Cannot create a counter")
     );
 
-    static TEAM_STORAGE: RefCell<StableBTreeMap<u64, Team, Memory>> =
-        RefCell::new(StableBTreeMap::init(
+    static TEAM_STORAGE: RefCell<StableRepo<u64, Team, Memory>> =
+        RefCell::new(StableRepo::new(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1)))
     ));
 
-    static COACH_STORAGE: RefCell<StableBTreeMap<u64, Coach, Memory>> =
-        RefCell::new(StableBTreeMap::init(
+    static COACH_STORAGE: RefCell<StableRepo<u64, Coach, Memory>> =
+        RefCell::new(StableRepo::new(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2)))
     ));
 
-    static STADIUM_STORAGE: RefCell<StableBTreeMap<u64, Stadium, Memory>> =
-        RefCell::new(StableBTreeMap::init(
+    static STADIUM_STORAGE: RefCell<StableRepo<u64, Stadium, Memory>> =
+        RefCell::new(StableRepo::new(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3)))
     ));
 
-    static MATCH_STORAGE: RefCell<StableBTreeMap<u64, Match, Memory>> =
-        RefCell::new(StableBTreeMap::init(
+    static MATCH_STORAGE: RefCell<StableRepo<u64, Match, Memory>> =
+        RefCell::new(StableRepo::new(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4)))
     ));
+
+    // Strictly increasing sequence number for the next operation log entry.
+    static OP_SEQ: RefCell<IdCell> = RefCell::new(
+        IdCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5))), 0)
+            .expect("Cannot create operation sequence counter")
+    );
+
+    static OP_LOG: RefCell<StableRepo<u64, Operation, Memory>> =
+        RefCell::new(StableRepo::new(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6)))
+    ));
+
+    static CHECKPOINT: RefCell<StableRepo<u64, Snapshot, Memory>> =
+        RefCell::new(StableRepo::new(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7)))
+    ));
+
+    // Secondary index over MATCH_STORAGE keyed by (match_date, id), kept in
+    // sync by index_match/unindex_match so date-range scans use the
+    // BTreeMap's native ordering instead of a full table scan.
+    static MATCH_BY_DATE: RefCell<StableBTreeMap<(u64, u64), (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8)))
+    ));
 }
 
-#[ic_cdk::update]
-fn add_team(payload: TeamPayload) -> Result<Team, CustomError> {
-    // Validation logic
-    if payload.name.is_empty()
-        || payload.manager.is_empty()
-        || payload.stadium.is_empty()
+/// Adds `m` to the `MATCH_BY_DATE` secondary index. Called by every handler
+/// that inserts a match into `MATCH_STORAGE`.
+fn index_match(m: &Match) {
+    MATCH_BY_DATE.with(|idx| idx.borrow_mut().insert((m.match_date, m.id), ()));
+}
+
+/// Removes the `(match_date, id)` entry from `MATCH_BY_DATE`. Called by
+/// every handler that removes or re-dates a match in `MATCH_STORAGE`.
+fn unindex_match(match_date: u64, id: u64) {
+    MATCH_BY_DATE.with(|idx| {
+        idx.borrow_mut().remove(&(match_date, id));
+    });
+}
+
+/// Derives the sequence number for the next log entry from `last` (the
+/// previous sequence issued) and `now`, so two operations appended within
+/// the same nanosecond still get distinct, strictly increasing keys. Pure
+/// so checkpoint-cadence and rollback tests can drive it with an injected
+/// `now` instead of `ic_cdk::api::time()`.
+fn next_seq_from(last: u64, now: u64) -> u64 {
+    if now > last {
+        now
+    } else {
+        last + 1
+    }
+}
+
+/// Issues the next operation-log sequence number from the live `OP_SEQ`
+/// counter and the current IC time.
+fn next_seq() -> u64 {
+    OP_SEQ.with(|seq| {
+        let last = *seq.borrow().get();
+        let next = next_seq_from(last, time());
+        seq.borrow_mut()
+            .set(next)
+            .expect("Cannot update operation sequence counter");
+        next
+    })
+}
+
+/// Bundles the four entity repos together so cross-cutting operations like
+/// checkpointing can take them as a single parameter instead of one each.
+struct EntityRepos<'a, T, C, S, M>
+where
+    T: Repository<u64, Team>,
+    C: Repository<u64, Coach>,
+    S: Repository<u64, Stadium>,
+    M: Repository<u64, Match>,
+{
+    teams: &'a RefCell<T>,
+    coaches: &'a RefCell<C>,
+    stadiums: &'a RefCell<S>,
+    matches: &'a RefCell<M>,
+}
+
+/// Appends `op` under `seq` to `log` and, every `KEEP_STATE_EVERY`
+/// operations, folds a fresh checkpoint of `repos` into `checkpoints`.
+/// Generic over `Repository` so the checkpoint cadence can be exercised
+/// under `cargo test` against `InMemoryRepo`.
+fn core_append_operation(
+    log: &RefCell<impl Repository<u64, Operation>>,
+    checkpoints: &RefCell<impl Repository<u64, Snapshot>>,
+    repos: &EntityRepos<
+        impl Repository<u64, Team>,
+        impl Repository<u64, Coach>,
+        impl Repository<u64, Stadium>,
+        impl Repository<u64, Match>,
+    >,
+    seq: u64,
+    op: Operation,
+) {
+    let log_len = {
+        let mut log = log.borrow_mut();
+        log.insert(seq, op);
+        log.len()
+    };
+
+    if log_len % KEEP_STATE_EVERY == 0 {
+        core_write_checkpoint(checkpoints, repos, seq);
+    }
+}
+
+/// Appends `op` to the live operation log, deriving its sequence number from
+/// the current IC time.
+fn append_operation(op: Operation) {
+    let seq = next_seq();
+    OP_LOG.with(|log| {
+        CHECKPOINT.with(|checkpoints| {
+            TEAM_STORAGE.with(|teams| {
+                COACH_STORAGE.with(|coaches| {
+                    STADIUM_STORAGE.with(|stadiums| {
+                        MATCH_STORAGE.with(|matches| {
+                            let repos = EntityRepos {
+                                teams,
+                                coaches,
+                                stadiums,
+                                matches,
+                            };
+                            core_append_operation(log, checkpoints, &repos, seq, op)
+                        })
+                    })
+                })
+            })
+        })
+    });
+}
+
+/// Snapshots `repos` and stores the result under `seq`, the sequence number
+/// of the last operation it reflects.
+fn core_write_checkpoint(
+    checkpoints: &RefCell<impl Repository<u64, Snapshot>>,
+    repos: &EntityRepos<
+        impl Repository<u64, Team>,
+        impl Repository<u64, Coach>,
+        impl Repository<u64, Stadium>,
+        impl Repository<u64, Match>,
+    >,
+    seq: u64,
+) {
+    let snapshot = Snapshot {
+        teams: repos.teams.borrow().iter().map(|(_, v)| v).collect(),
+        coaches: repos.coaches.borrow().iter().map(|(_, v)| v).collect(),
+        stadiums: repos.stadiums.borrow().iter().map(|(_, v)| v).collect(),
+        matches: repos.matches.borrow().iter().map(|(_, v)| v).collect(),
+    };
+    checkpoints.borrow_mut().insert(seq, snapshot);
+}
+
+/// Applies a single logged operation on top of an in-memory snapshot during
+/// replay. Keeps replay deterministic and independent of live storage.
+fn apply_operation(snapshot: &mut Snapshot, op: Operation) {
+    match op {
+        Operation::AddTeam(team) => snapshot.teams.push(team),
+        Operation::UpdateTeam { id, team } => {
+            if let Some(existing) = snapshot.teams.iter_mut().find(|t| t.id == id) {
+                *existing = team;
+            }
+        }
+        Operation::DeleteTeam(id) => snapshot.teams.retain(|t| t.id != id),
+        Operation::AddCoach(coach) => snapshot.coaches.push(coach),
+        Operation::UpdateCoach { id, coach } => {
+            if let Some(existing) = snapshot.coaches.iter_mut().find(|c| c.id == id) {
+                *existing = coach;
+            }
+        }
+        Operation::DeleteCoach(id) => snapshot.coaches.retain(|c| c.id != id),
+        Operation::AddStadium(stadium) => snapshot.stadiums.push(stadium),
+        Operation::UpdateStadium { id, stadium } => {
+            if let Some(existing) = snapshot.stadiums.iter_mut().find(|s| s.id == id) {
+                *existing = stadium;
+            }
+        }
+        Operation::DeleteStadium(id) => snapshot.stadiums.retain(|s| s.id != id),
+        Operation::AddMatch(m) => snapshot.matches.push(m),
+        Operation::UpdateMatch { id, match_ } => {
+            if let Some(existing) = snapshot.matches.iter_mut().find(|m| m.id == id) {
+                *existing = match_;
+            }
+        }
+        Operation::DeleteMatch(id) => snapshot.matches.retain(|m| m.id != id),
+    }
+}
+
+/// Rebuilds the registry state as of `seq` by loading the most recent entry
+/// of `checkpoints` with key `<= seq` and replaying every operation logged
+/// in `log` in the half-open range `(checkpoint_seq, seq]` on top of it, in
+/// key order. Generic over `Repository` so checkpoint-boundary replay can be
+/// exercised under `cargo test` against `InMemoryRepo`.
+fn core_rebuild_state_at(
+    checkpoints: &RefCell<impl Repository<u64, Snapshot>>,
+    log: &RefCell<impl Repository<u64, Operation>>,
+    seq: u64,
+) -> Snapshot {
+    let (checkpoint_seq, mut snapshot) = checkpoints
+        .borrow()
+        .range(..=seq)
+        .last()
+        .unwrap_or((0, Snapshot::default()));
+
+    for (_, op) in log.borrow().range(checkpoint_seq + 1..=seq) {
+        apply_operation(&mut snapshot, op);
+    }
+
+    snapshot
+}
+
+/// Rebuilds the live registry state as of `seq`, without touching live
+/// storage.
+fn rebuild_state_at(seq: u64) -> Snapshot {
+    CHECKPOINT.with(|checkpoints| OP_LOG.with(|log| core_rebuild_state_at(checkpoints, log, seq)))
+}
+
+/// Returns the registry state as of the operation with sequence number
+/// `seq`, without touching live storage.
+#[ic_cdk::query]
+fn get_state_at(seq: u64) -> Snapshot {
+    rebuild_state_at(seq)
+}
+
+/// Deletes every `log`/`checkpoints` entry keyed past `seq`, so a rollback's
+/// discarded tail can never be replayed again by a later rebuild. Generic
+/// over `Repository` so rollback-then-append behavior can be exercised
+/// under `cargo test` against `InMemoryRepo`.
+fn core_discard_log_after(
+    log: &RefCell<impl Repository<u64, Operation>>,
+    checkpoints: &RefCell<impl Repository<u64, Snapshot>>,
+    seq: u64,
+) {
+    let stale_ops: Vec<u64> = log.borrow().range(seq + 1..).map(|(k, _)| k).collect();
     {
-        return Err(CustomError::EmptyFields {
-            msg: "Please fill in all the required fields to add a team".to_string(),
-        });
+        let mut log = log.borrow_mut();
+        for k in stale_ops {
+            log.remove(&k);
+        }
+    }
+
+    let stale_checkpoints: Vec<u64> =
+        checkpoints.borrow().range(seq + 1..).map(|(k, _)| k).collect();
+    let mut checkpoints = checkpoints.borrow_mut();
+    for k in stale_checkpoints {
+        checkpoints.remove(&k);
+    }
+}
+
+/// Deletes every live `OP_LOG`/`CHECKPOINT` entry keyed past `seq`, so a
+/// rollback's discarded tail can never be replayed again by a later
+/// `get_state_at`/`rollback_to` call.
+fn discard_log_after(seq: u64) {
+    OP_LOG.with(|log| CHECKPOINT.with(|checkpoints| core_discard_log_after(log, checkpoints, seq)));
+}
+
+/// Replaces every entry in `storage` with `entities`, keyed by each entity's
+/// own id via `id_of`.
+fn restore_from_snapshot<V: Clone>(
+    storage: &RefCell<impl Repository<u64, V>>,
+    entities: &[V],
+    id_of: impl Fn(&V) -> u64,
+) {
+    let mut storage = storage.borrow_mut();
+    let keys: Vec<u64> = storage.iter().map(|(k, _)| k).collect();
+    for key in keys {
+        storage.remove(&key);
     }
+    for entity in entities {
+        storage.insert(id_of(entity), entity.clone());
+    }
+}
 
-    let id = ID_COUNTER.with(|counter| {
+/// Rebuilds state as of `seq` and commits it back into the live maps,
+/// discarding the effect of any later operation. Returns the restored state.
+#[ic_cdk::update]
+fn rollback_to(seq: u64) -> Snapshot {
+    let snapshot = rebuild_state_at(seq);
+    discard_log_after(seq);
+
+    TEAM_STORAGE.with(|s| restore_from_snapshot(s, &snapshot.teams, |t| t.id));
+    COACH_STORAGE.with(|s| restore_from_snapshot(s, &snapshot.coaches, |c| c.id));
+    STADIUM_STORAGE.with(|s| restore_from_snapshot(s, &snapshot.stadiums, |st| st.id));
+    MATCH_STORAGE.with(|s| restore_from_snapshot(s, &snapshot.matches, |m| m.id));
+
+    // MATCH_BY_DATE is a secondary index over MATCH_STORAGE, not one of the
+    // four maps captured in `Snapshot`, so it has to be rebuilt from the
+    // restored matches rather than overwritten from the snapshot directly.
+    MATCH_BY_DATE.with(|idx| {
+        let mut idx = idx.borrow_mut();
+        let keys: Vec<(u64, u64)> = idx.range(..).map(|(k, _)| k).collect();
+        for key in keys {
+            idx.remove(&key);
+        }
+    });
+    for m in &snapshot.matches {
+        index_match(m);
+    }
+
+    snapshot
+}
+
+/// Checks referential integrity ahead of an insert/update: returns
+/// `Err(CustomError::InvalidReference)` when `id` has no matching row in
+/// `storage`.
+fn require_exists<V>(
+    storage: &RefCell<impl Repository<u64, V>>,
+    id: u64,
+    entity: &str,
+) -> Result<(), CustomError> {
+    if storage.borrow().get(&id).is_some() {
+        Ok(())
+    } else {
+        Err(CustomError::InvalidReference(format!(
+            "{} with ID {} does not exist",
+            entity, id
+        )))
+    }
+}
+
+/// Looks up `id` in `storage`, or builds a `NotFound` error with `not_found`
+/// on a miss. Generic over `Repository` (see `repository` module docs) so
+/// this and the helpers below run the same against `StableRepo` on the
+/// canister and `InMemoryRepo` in tests.
+fn lookup<V: Clone>(
+    storage: &RefCell<impl Repository<u64, V>>,
+    id: u64,
+    not_found: impl FnOnce() -> CustomError,
+) -> Result<V, CustomError> {
+    storage.borrow().get(&id).ok_or_else(not_found)
+}
+
+/// Applies `mutate` to the row at `id` in `storage` and writes it back, or
+/// builds a `NotFound` error with `not_found` on a miss.
+fn mutate_existing<V: Clone>(
+    storage: &RefCell<impl Repository<u64, V>>,
+    id: u64,
+    not_found: impl FnOnce() -> CustomError,
+    mutate: impl FnOnce(&mut V),
+) -> Result<V, CustomError> {
+    let mut storage = storage.borrow_mut();
+    if let Some(mut existing) = storage.get(&id) {
+        mutate(&mut existing);
+        storage.insert(id, existing.clone());
+        Ok(existing)
+    } else {
+        Err(not_found())
+    }
+}
+
+/// Removes the row at `id` from `storage`, or builds a `NotFound` error with
+/// `not_found` on a miss.
+fn remove_existing<V>(
+    storage: &RefCell<impl Repository<u64, V>>,
+    id: u64,
+    not_found: impl FnOnce() -> CustomError,
+) -> Result<V, CustomError> {
+    storage.borrow_mut().remove(&id).ok_or_else(not_found)
+}
+
+/// Issues the next globally unique entity ID, shared across all four entity
+/// kinds.
+fn next_id() -> u64 {
+    ID_COUNTER.with(|counter| {
         let current_value = *counter.borrow().get();
-        counter.borrow_mut().set(current_value + 1);
+        counter
+            .borrow_mut()
+            .set(current_value + 1)
+            .expect("Cannot update ID counter");
         current_value + 1
-    });
+    })
+}
+
+/// Validates `payload`, checks that its stadium exists in `stadiums`, and
+/// inserts a new team with `id` into `storage`.
+fn core_add_team(
+    storage: &RefCell<impl Repository<u64, Team>>,
+    stadiums: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: TeamPayload,
+) -> Result<Team, CustomError> {
+    if payload.name.is_empty() || payload.manager.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "Please fill in all the required fields to add a team".to_string(),
+        ));
+    }
+    require_exists(stadiums, payload.stadium, "Stadium")?;
 
     let team = Team {
         id,
-        name: payload.
         name: payload.name,
         manager: payload.manager,
         stadium: payload.stadium,
     };
+    storage.borrow_mut().insert(id, team.clone());
+    Ok(team)
+}
 
-    TEAM_STORAGE.with(|storage| storage.borrow_mut().insert(id, team.clone()));
+#[ic_cdk::update]
+fn add_team(payload: TeamPayload) -> Result<Team, CustomError> {
+    let id = next_id();
+    let team = TEAM_STORAGE.with(|storage| {
+        STADIUM_STORAGE.with(|stadiums| core_add_team(storage, stadiums, id, payload))
+    })?;
+    append_operation(Operation::AddTeam(team.clone()));
     Ok(team)
 }
 
 #[ic_cdk::query]
 fn get_team(id: u64) -> Result<Team, CustomError> {
     TEAM_STORAGE.with(|storage| {
-        if let Some(team) = storage.borrow().get(&id) {
-            Ok(team.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Team with ID {} cannot be found",
-                id
-            )))
-        }
+        lookup(storage, id, || {
+            CustomError::NotFound(format!("Team with ID {} cannot be found", id))
+        })
+    })
+}
+
+/// Checks that no coach or match still references `id`, then removes the
+/// team from `storage`.
+fn core_delete_team(
+    storage: &RefCell<impl Repository<u64, Team>>,
+    coaches: &RefCell<impl Repository<u64, Coach>>,
+    matches: &RefCell<impl Repository<u64, Match>>,
+    id: u64,
+) -> Result<Team, CustomError> {
+    let referenced_by_coach = coaches.borrow().iter().any(|(_, c)| c.team == id);
+    if referenced_by_coach {
+        return Err(CustomError::InvalidReference(format!(
+            "Team with ID {} still has coaches assigned to it",
+            id
+        )));
+    }
+    let referenced_by_match = matches
+        .borrow()
+        .iter()
+        .any(|(_, m)| m.home_team == id || m.away_team == id);
+    if referenced_by_match {
+        return Err(CustomError::InvalidReference(format!(
+            "Team with ID {} still has matches scheduled",
+            id
+        )));
+    }
+
+    remove_existing(storage, id, || {
+        CustomError::NotFound(format!("Team with ID {} not found", id))
     })
 }
 
 #[ic_cdk::update]
 fn delete_team(id: u64) -> Result<(), CustomError> {
     TEAM_STORAGE.with(|storage| {
-        if storage.borrow_mut().remove(&id).is_some() {
-            Ok(())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Team with ID {} not found",
-                id
-            )))
-        }
-    })
+        COACH_STORAGE.with(|coaches| {
+            MATCH_STORAGE.with(|matches| core_delete_team(storage, coaches, matches, id))
+        })
+    })?;
+    append_operation(Operation::DeleteTeam(id));
+    Ok(())
 }
 
-#[ic_cdk::update]
-fn update_team(id: u64, payload: TeamPayload) -> Result<Team, CustomError> {
-    // Validation logic
-    if payload.name.is_empty()
-        || payload.manager.is_empty()
-        || payload.stadium.is_empty()
-    {
-        return Err(CustomError::EmptyFields {
-            msg: "You must fill all of the required fields".to_string(),
-        });
+/// Validates `payload`, checks that its stadium exists in `stadiums`, and
+/// applies it to the team at `id` in `storage`.
+fn core_update_team(
+    storage: &RefCell<impl Repository<u64, Team>>,
+    stadiums: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: TeamPayload,
+) -> Result<Team, CustomError> {
+    if payload.name.is_empty() || payload.manager.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "You must fill all of the required fields".to_string(),
+        ));
     }
+    require_exists(stadiums, payload.stadium, "Stadium")?;
 
-    TEAM_STORAGE.with(|storage| {
-        if let Some(mut existing_team) = storage.borrow_mut().get_mut(&id) {
-            // Update the fields
-            existing_team.name = payload.name;
-            existing_team.manager = payload.manager;
-            existing_team.stadium = payload.stadium;
-
-            Ok(existing_team.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Team with ID {} not found",
-                id
-            )))
-        }
-    })
+    mutate_existing(
+        storage,
+        id,
+        || CustomError::NotFound(format!("Team with ID {} not found", id)),
+        |team| {
+            team.name = payload.name;
+            team.manager = payload.manager;
+            team.stadium = payload.stadium;
+        },
+    )
 }
 
 #[ic_cdk::update]
-fn add_coach(payload: CoachPayload) -> Result<Coach, CustomError> {
-    // Validation logic
-    if payload.name.is_empty() || payload.team.is_empty() {
-        return Err(CustomError::EmptyFields {
-            msg: "You must fill in all the required fields".to_string(),
-        });
-    }
+fn update_team(id: u64, payload: TeamPayload) -> Result<Team, CustomError> {
+    let updated = TEAM_STORAGE.with(|storage| {
+        STADIUM_STORAGE.with(|stadiums| core_update_team(storage, stadiums, id, payload))
+    })?;
 
-    let id = ID_COUNTER.with(|counter| {
-        let current_value = *counter.borrow().get();
-        counter.borrow_mut().set(current_value + 1);
-        current_value + 1
+    append_operation(Operation::UpdateTeam {
+        id,
+        team: updated.clone(),
     });
+    Ok(updated)
+}
+
+/// Validates `payload`, checks that its team exists in `teams`, and inserts
+/// a new coach with `id` into `storage`.
+fn core_add_coach(
+    storage: &RefCell<impl Repository<u64, Coach>>,
+    teams: &RefCell<impl Repository<u64, Team>>,
+    id: u64,
+    payload: CoachPayload,
+) -> Result<Coach, CustomError> {
+    if payload.name.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "You must fill in all the required fields".to_string(),
+        ));
+    }
+    require_exists(teams, payload.team, "Team")?;
 
     let coach = Coach {
         id,
         name: payload.name,
         team: payload.team,
     };
+    storage.borrow_mut().insert(id, coach.clone());
+    Ok(coach)
+}
 
-    COACH_STORAGE.with(|storage| storage.borrow_mut().insert(id, coach.clone()));
+#[ic_cdk::update]
+fn add_coach(payload: CoachPayload) -> Result<Coach, CustomError> {
+    let id = next_id();
+    let coach = COACH_STORAGE.with(|storage| {
+        TEAM_STORAGE.with(|teams| core_add_coach(storage, teams, id, payload))
+    })?;
+    append_operation(Operation::AddCoach(coach.clone()));
     Ok(coach)
 }
 
 #[ic_cdk::query]
 fn get_coach(id: u64) -> Result<Coach, CustomError> {
     COACH_STORAGE.with(|storage| {
-        if let Some(coach) = storage.borrow().get(&id) {
-            Ok(coach.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Coach with ID {} cannot be found",
-                id
-            )))
-        }
+        lookup(storage, id, || {
+            CustomError::NotFound(format!("Coach with ID {} cannot be found", id))
+        })
     })
 }
 
 #[ic_cdk::update]
 fn delete_coach(id: u64) -> Result<(), CustomError> {
     COACH_STORAGE.with(|storage| {
-        if storage.borrow_mut().remove(&id).is_some() {
-            Ok(())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Coach with ID {} not found",
-                id
-            )))
-        }
-    })
+        remove_existing(storage, id, || {
+            CustomError::NotFound(format!("Coach with ID {} not found", id))
+        })
+    })?;
+    append_operation(Operation::DeleteCoach(id));
+    Ok(())
 }
 
-#[ic_cdk::update]
-fn update_coach(id: u64, payload: CoachPayload) -> Result<Coach, CustomError> {
-    // Validation logic
-    if payload.name.is_empty() || payload.team.is_empty() {
-        return Err(CustomError::EmptyFields {
-            msg: "You must fill in all the required fields".to_string(),
-        });
+/// Validates `payload`, checks that its team exists in `teams`, and applies
+/// it to the coach at `id` in `storage`.
+fn core_update_coach(
+    storage: &RefCell<impl Repository<u64, Coach>>,
+    teams: &RefCell<impl Repository<u64, Team>>,
+    id: u64,
+    payload: CoachPayload,
+) -> Result<Coach, CustomError> {
+    if payload.name.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "You must fill in all the required fields".to_string(),
+        ));
     }
+    require_exists(teams, payload.team, "Team")?;
 
-    COACH_STORAGE.with(|storage| {
-        if let Some(mut existing_coach) = storage.borrow_mut().get_mut(&id) {
-            // Update the fields
-            existing_coach.name = payload.name;
-            existing_coach.team = payload.team;
-
-            Ok(existing_coach.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Coach with ID {} not found",
-                id
-            )))
-        }
-    })
+    mutate_existing(
+        storage,
+        id,
+        || CustomError::NotFound(format!("Coach with ID {} not found", id)),
+        |coach| {
+            coach.name = payload.name;
+            coach.team = payload.team;
+        },
+    )
 }
 
 #[ic_cdk::update]
-fn add_stadium(payload: StadiumPayload) -> Result<Stadium, CustomError> {
-    // Validation logic
-    if payload.name.is_empty() || payload.location.is_empty() || payload.capacity == 0 {
-        return Err(CustomError::EmptyFields {
-            msg: "Please fill in all the required fields".to_string(),
-        });
-    }
+fn update_coach(id: u64, payload: CoachPayload) -> Result<Coach, CustomError> {
+    let updated = COACH_STORAGE.with(|storage| {
+        TEAM_STORAGE.with(|teams| core_update_coach(storage, teams, id, payload))
+    })?;
 
-    let id = ID_COUNTER.with(|counter| {
-        let current_value = *counter.borrow().get();
-        counter.borrow_mut().set(current_value + 1);
-        current_value + 1
+    append_operation(Operation::UpdateCoach {
+        id,
+        coach: updated.clone(),
     });
+    Ok(updated)
+}
+
+/// Validates `payload` and inserts a new stadium with `id` into `storage`.
+fn core_add_stadium(
+    storage: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: StadiumPayload,
+) -> Result<Stadium, CustomError> {
+    if payload.name.is_empty() || payload.location.is_empty() || payload.capacity == 0 {
+        return Err(CustomError::EmptyFields(
+            "Please fill in all the required fields".to_string(),
+        ));
+    }
 
     let stadium = Stadium {
         id,
@@ -402,65 +920,1248 @@ fn add_stadium(payload: StadiumPayload) -> Result<Stadium, CustomError> {
         location: payload.location,
         capacity: payload.capacity,
     };
+    storage.borrow_mut().insert(id, stadium.clone());
+    Ok(stadium)
+}
 
-    STADIUM_STORAGE.with(|storage| {
-        storage.borrow_mut().insert(id, stadium.clone());
-    });
-
+#[ic_cdk::update]
+fn add_stadium(payload: StadiumPayload) -> Result<Stadium, CustomError> {
+    let id = next_id();
+    let stadium = STADIUM_STORAGE.with(|storage| core_add_stadium(storage, id, payload))?;
+    append_operation(Operation::AddStadium(stadium.clone()));
     Ok(stadium)
 }
 
 #[ic_cdk::query]
 fn get_stadium(id: u64) -> Result<Stadium, CustomError> {
     STADIUM_STORAGE.with(|storage| {
-        if let Some(stadium) = storage.borrow().get(&id) {
-            Ok(stadium.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Stadium with ID {} not found",
-                id
-            )))
-        }
+        lookup(storage, id, || {
+            CustomError::NotFound(format!("Stadium with ID {} not found", id))
+        })
     })
 }
 
-#[ic_cdk::update]
-fn update_stadium(id: u64, payload: StadiumPayload) -> Result<Stadium, CustomError> {
-    // Validation logic
+/// Validates `payload` and applies it to the stadium at `id` in `storage`.
+fn core_update_stadium(
+    storage: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: StadiumPayload,
+) -> Result<Stadium, CustomError> {
     if payload.name.is_empty() || payload.location.is_empty() || payload.capacity == 0 {
-        return Err(CustomError::EmptyFields {
-            msg: "Please fill in all the required fields".to_string(),
-        });
+        return Err(CustomError::EmptyFields(
+            "Please fill in all the required fields".to_string(),
+        ));
     }
 
-    STADIUM_STORAGE.with(|storage| {
-        if let Some(mut existing_stadium) = storage.borrow_mut().get_mut(&id) {
-            existing_stadium.name = payload.name;
-            existing_stadium.location = payload.location;
-            existing_stadium.capacity = payload.capacity;
+    mutate_existing(
+        storage,
+        id,
+        || CustomError::NotFound(format!("Stadium with ID {} not found", id)),
+        |stadium| {
+            stadium.name = payload.name;
+            stadium.location = payload.location;
+            stadium.capacity = payload.capacity;
+        },
+    )
+}
 
-            Ok(existing_stadium.clone())
-        } else {
-            Err(CustomError::NotFound(format!(
-                "Stadium with ID {} not found",
-                id
-            )))
-        }
+#[ic_cdk::update]
+fn update_stadium(id: u64, payload: StadiumPayload) -> Result<Stadium, CustomError> {
+    let updated = STADIUM_STORAGE.with(|storage| core_update_stadium(storage, id, payload))?;
+
+    append_operation(Operation::UpdateStadium {
+        id,
+        stadium: updated.clone(),
+    });
+    Ok(updated)
+}
+
+/// Checks that no team or match still references `id`, then removes the
+/// stadium from `storage`.
+fn core_delete_stadium(
+    storage: &RefCell<impl Repository<u64, Stadium>>,
+    teams: &RefCell<impl Repository<u64, Team>>,
+    matches: &RefCell<impl Repository<u64, Match>>,
+    id: u64,
+) -> Result<Stadium, CustomError> {
+    let referenced_by_team = teams.borrow().iter().any(|(_, t)| t.stadium == id);
+    if referenced_by_team {
+        return Err(CustomError::InvalidReference(format!(
+            "Stadium with ID {} is still home to a team",
+            id
+        )));
+    }
+    let referenced_by_match = matches.borrow().iter().any(|(_, m)| m.venue == id);
+    if referenced_by_match {
+        return Err(CustomError::InvalidReference(format!(
+            "Stadium with ID {} still has matches scheduled",
+            id
+        )));
+    }
+
+    remove_existing(storage, id, || {
+        CustomError::NotFound(format!("Stadium with ID {} not found", id))
     })
 }
 
 #[ic_cdk::update]
 fn delete_stadium(id: u64) -> Result<(), CustomError> {
     STADIUM_STORAGE.with(|storage| {
-        if storage.borrow_mut().remove(&id).is_some() {
-            Ok(())
+        TEAM_STORAGE.with(|teams| {
+            MATCH_STORAGE.with(|matches| core_delete_stadium(storage, teams, matches, id))
+        })
+    })?;
+    append_operation(Operation::DeleteStadium(id));
+    Ok(())
+}
+
+/// Validates `payload`, checks that its teams and venue exist, parses its
+/// date, and inserts a new match with `id` into `storage`.
+fn core_add_match(
+    storage: &RefCell<impl Repository<u64, Match>>,
+    teams: &RefCell<impl Repository<u64, Team>>,
+    stadiums: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: MatchPayload,
+) -> Result<Match, CustomError> {
+    if payload.match_date.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "Please fill in all the required fields to schedule a match".to_string(),
+        ));
+    }
+    if payload.home_team == payload.away_team {
+        return Err(CustomError::InvalidReference(
+            "A team cannot play a match against itself".to_string(),
+        ));
+    }
+    require_exists(teams, payload.home_team, "Team")?;
+    require_exists(teams, payload.away_team, "Team")?;
+    require_exists(stadiums, payload.venue, "Stadium")?;
+    let match_date = parse_match_date(&payload.match_date, &payload.date_conversion)?;
+
+    let m = Match {
+        id,
+        home_team: payload.home_team,
+        away_team: payload.away_team,
+        venue: payload.venue,
+        match_date,
+        home_goals: None,
+        away_goals: None,
+    };
+    storage.borrow_mut().insert(id, m.clone());
+    Ok(m)
+}
+
+#[ic_cdk::update]
+fn add_match(payload: MatchPayload) -> Result<Match, CustomError> {
+    let id = next_id();
+    let m = MATCH_STORAGE.with(|storage| {
+        TEAM_STORAGE.with(|teams| {
+            STADIUM_STORAGE.with(|stadiums| core_add_match(storage, teams, stadiums, id, payload))
+        })
+    })?;
+    index_match(&m);
+    append_operation(Operation::AddMatch(m.clone()));
+    Ok(m)
+}
+
+#[ic_cdk::query]
+fn get_match(id: u64) -> Result<Match, CustomError> {
+    MATCH_STORAGE.with(|storage| {
+        lookup(storage, id, || {
+            CustomError::NotFound(format!("Match with ID {} cannot be found", id))
+        })
+    })
+}
+
+/// Validates `payload`, checks that its teams and venue exist, parses its
+/// date, and applies it to the match at `id` in `storage`.
+fn core_update_match(
+    storage: &RefCell<impl Repository<u64, Match>>,
+    teams: &RefCell<impl Repository<u64, Team>>,
+    stadiums: &RefCell<impl Repository<u64, Stadium>>,
+    id: u64,
+    payload: MatchPayload,
+) -> Result<Match, CustomError> {
+    if payload.match_date.is_empty() {
+        return Err(CustomError::EmptyFields(
+            "Please fill in all the required fields to schedule a match".to_string(),
+        ));
+    }
+    if payload.home_team == payload.away_team {
+        return Err(CustomError::InvalidReference(
+            "A team cannot play a match against itself".to_string(),
+        ));
+    }
+    require_exists(teams, payload.home_team, "Team")?;
+    require_exists(teams, payload.away_team, "Team")?;
+    require_exists(stadiums, payload.venue, "Stadium")?;
+    let match_date = parse_match_date(&payload.match_date, &payload.date_conversion)?;
+
+    mutate_existing(
+        storage,
+        id,
+        || CustomError::NotFound(format!("Match with ID {} not found", id)),
+        |existing| {
+            existing.home_team = payload.home_team;
+            existing.away_team = payload.away_team;
+            existing.venue = payload.venue;
+            existing.match_date = match_date;
+        },
+    )
+}
+
+#[ic_cdk::update]
+fn update_match(id: u64, payload: MatchPayload) -> Result<Match, CustomError> {
+    let old_date = MATCH_STORAGE.with(|storage| {
+        lookup(storage, id, || {
+            CustomError::NotFound(format!("Match with ID {} not found", id))
+        })
+    })?
+    .match_date;
+
+    let updated = MATCH_STORAGE.with(|storage| {
+        TEAM_STORAGE.with(|teams| {
+            STADIUM_STORAGE
+                .with(|stadiums| core_update_match(storage, teams, stadiums, id, payload))
+        })
+    })?;
+
+    if old_date != updated.match_date {
+        unindex_match(old_date, id);
+        index_match(&updated);
+    }
+    append_operation(Operation::UpdateMatch {
+        id,
+        match_: updated.clone(),
+    });
+    Ok(updated)
+}
+
+#[ic_cdk::update]
+fn delete_match(id: u64) -> Result<(), CustomError> {
+    let removed = MATCH_STORAGE.with(|storage| {
+        remove_existing(storage, id, || {
+            CustomError::NotFound(format!("Match with ID {} not found", id))
+        })
+    })?;
+    unindex_match(removed.match_date, id);
+    append_operation(Operation::DeleteMatch(id));
+    Ok(())
+}
+
+/// Records the final score for a scheduled match.
+#[ic_cdk::update]
+fn record_result(id: u64, home_goals: u32, away_goals: u32) -> Result<Match, CustomError> {
+    let updated = MATCH_STORAGE.with(|storage| {
+        mutate_existing(
+            storage,
+            id,
+            || CustomError::NotFound(format!("Match with ID {} not found", id)),
+            |existing| {
+                existing.home_goals = Some(home_goals);
+                existing.away_goals = Some(away_goals);
+            },
+        )
+    })?;
+
+    append_operation(Operation::UpdateMatch {
+        id,
+        match_: updated.clone(),
+    });
+    Ok(updated)
+}
+
+/// Looks up a team's display name by ID, falling back to the ID itself if
+/// the team can no longer be found.
+fn team_name(id: u64) -> String {
+    TEAM_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .get(&id)
+            .map(|t| t.name)
+            .unwrap_or_else(|| id.to_string())
+    })
+}
+
+/// A single row of the league table.
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct StandingRow {
+    team: String,
+    played: u32,
+    won: u32,
+    drawn: u32,
+    lost: u32,
+    goals_for: u32,
+    goals_against: u32,
+    goal_difference: i64,
+    points: u32,
+}
+
+/// Folds over every completed match (one with a recorded result) and
+/// computes the league table: played/won/drawn/lost, goals for/against,
+/// goal difference, and points (3 for a win, 1 for a draw), sorted by
+/// points, then goal difference, then goals scored. Pure and IC-independent
+/// so it can be unit tested directly, given `matches` and a `team_name`
+/// resolver instead of reading `MATCH_STORAGE`/`TEAM_STORAGE` itself.
+fn compute_standings(matches: &[Match], team_name: impl Fn(u64) -> String) -> Vec<StandingRow> {
+    let mut table: HashMap<u64, StandingRow> = HashMap::new();
+
+    for m in matches {
+        let (Some(home_goals), Some(away_goals)) = (m.home_goals, m.away_goals) else {
+            continue;
+        };
+
+        let home = table.entry(m.home_team).or_insert_with(|| StandingRow {
+            team: team_name(m.home_team),
+            ..Default::default()
+        });
+        home.played += 1;
+        home.goals_for += home_goals;
+        home.goals_against += away_goals;
+        if home_goals > away_goals {
+            home.won += 1;
+            home.points += 3;
+        } else if home_goals == away_goals {
+            home.drawn += 1;
+            home.points += 1;
+        } else {
+            home.lost += 1;
+        }
+
+        let away = table.entry(m.away_team).or_insert_with(|| StandingRow {
+            team: team_name(m.away_team),
+            ..Default::default()
+        });
+        away.played += 1;
+        away.goals_for += away_goals;
+        away.goals_against += home_goals;
+        if away_goals > home_goals {
+            away.won += 1;
+            away.points += 3;
+        } else if away_goals == home_goals {
+            away.drawn += 1;
+            away.points += 1;
         } else {
-            Err(CustomError::NotFound(format!(
-                "Stadium with ID {} not found",
-                id
-            )))
+            away.lost += 1;
         }
+    }
+
+    let mut rows: Vec<StandingRow> = table
+        .into_values()
+        .map(|mut row| {
+            row.goal_difference = row.goals_for as i64 - row.goals_against as i64;
+            row
+        })
+        .collect();
+
+    rows.sort_by(|a, b| {
+        b.points
+            .cmp(&a.points)
+            .then(b.goal_difference.cmp(&a.goal_difference))
+            .then(b.goals_for.cmp(&a.goals_for))
+    });
+
+    rows
+}
+
+#[ic_cdk::query]
+fn get_standings() -> Vec<StandingRow> {
+    let matches: Vec<Match> =
+        MATCH_STORAGE.with(|storage| storage.borrow().iter().map(|(_, m)| m).collect());
+    compute_standings(&matches, team_name)
+}
+
+/// Renders a stored match's `match_date` back out as a string formatted per
+/// the given `strftime`-style format.
+#[ic_cdk::query]
+fn format_match_date(id: u64, fmt: String) -> Result<String, CustomError> {
+    let m = get_match(id)?;
+    let seconds = (m.match_date / 1_000_000_000) as i64;
+    let datetime = DateTime::<Utc>::from_timestamp(seconds, 0).ok_or_else(|| {
+        CustomError::InvalidDate(format!(
+            "Match {} has a match_date that cannot be rendered as a calendar date",
+            id
+        ))
+    })?;
+
+    let mut rendered = String::new();
+    write!(rendered, "{}", datetime.format(&fmt))
+        .map_err(|_| CustomError::InvalidDate(format!("'{}' is not a valid date format", fmt)))?;
+    Ok(rendered)
+}
+
+/// Matches whose `match_date` falls in the inclusive interval
+/// `[start_ts, end_ts]`, ordered by date via `MATCH_BY_DATE`.
+#[ic_cdk::query]
+fn list_matches_between(start_ts: u64, end_ts: u64) -> Vec<Match> {
+    MATCH_BY_DATE.with(|idx| {
+        idx.borrow()
+            .range((start_ts, 0)..=(end_ts, u64::MAX))
+            .filter_map(|((_, id), _)| MATCH_STORAGE.with(|s| s.borrow().get(&id)))
+            .collect()
+    })
+}
+
+/// All matches where the team with the given ID plays either at home or
+/// away.
+#[ic_cdk::query]
+fn list_matches_for_team(team: u64) -> Vec<Match> {
+    MATCH_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|(_, m)| m.home_team == team || m.away_team == team)
+            .map(|(_, m)| m)
+            .collect()
+    })
+}
+
+/// Up to `limit` matches scheduled at or after `now`, earliest first.
+#[ic_cdk::query]
+fn list_upcoming_matches(now: u64, limit: u32) -> Vec<Match> {
+    MATCH_BY_DATE.with(|idx| {
+        idx.borrow()
+            .range((now, 0)..=(u64::MAX, u64::MAX))
+            .filter_map(|((_, id), _)| MATCH_STORAGE.with(|s| s.borrow().get(&id)))
+            .take(limit as usize)
+            .collect()
     })
 }
 
 ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repository::InMemoryRepo;
+
+    #[test]
+    fn lookup_hit_and_miss() {
+        let storage = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        storage.borrow_mut().insert(
+            1,
+            Stadium {
+                id: 1,
+                name: "Anfield".to_string(),
+                location: "Liverpool".to_string(),
+                capacity: 54000,
+            },
+        );
+
+        let found = lookup(&storage, 1, || {
+            CustomError::NotFound("missing".to_string())
+        })
+        .unwrap();
+        assert_eq!(found.name, "Anfield");
+
+        let err = lookup(&storage, 2, || {
+            CustomError::NotFound("Stadium with ID 2 not found".to_string())
+        })
+        .unwrap_err();
+        assert!(matches!(err, CustomError::NotFound(msg) if msg.contains("2")));
+    }
+
+    #[test]
+    fn mutate_existing_updates_and_reports_missing() {
+        let storage = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        storage.borrow_mut().insert(
+            1,
+            Team {
+                id: 1,
+                name: "Reds".to_string(),
+                manager: "Klopp".to_string(),
+                stadium: 1,
+            },
+        );
+
+        let updated = mutate_existing(
+            &storage,
+            1,
+            || CustomError::NotFound("Team with ID 1 not found".to_string()),
+            |team| team.manager = "Slot".to_string(),
+        )
+        .unwrap();
+        assert_eq!(updated.manager, "Slot");
+        assert_eq!(storage.borrow().get(&1).unwrap().manager, "Slot");
+
+        let err = mutate_existing(
+            &storage,
+            2,
+            || CustomError::NotFound("Team with ID 2 not found".to_string()),
+            |team| team.manager = "Nobody".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, CustomError::NotFound(_)));
+    }
+
+    #[test]
+    fn remove_existing_removes_and_reports_missing() {
+        let storage = RefCell::new(InMemoryRepo::<u64, Coach>::new());
+        storage.borrow_mut().insert(
+            1,
+            Coach {
+                id: 1,
+                name: "Arne".to_string(),
+                team: 1,
+            },
+        );
+
+        let removed = remove_existing(&storage, 1, || {
+            CustomError::NotFound("Coach with ID 1 not found".to_string())
+        })
+        .unwrap();
+        assert_eq!(removed.name, "Arne");
+        assert!(storage.borrow().get(&1).is_none());
+
+        let err = remove_existing(&storage, 1, || {
+            CustomError::NotFound("Coach with ID 1 not found".to_string())
+        })
+        .unwrap_err();
+        assert!(matches!(err, CustomError::NotFound(_)));
+    }
+
+    #[test]
+    fn require_exists_checks_referential_integrity() {
+        let storage = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        storage.borrow_mut().insert(1, Stadium::default());
+
+        assert!(require_exists(&storage, 1, "Stadium").is_ok());
+        assert!(matches!(
+            require_exists(&storage, 2, "Stadium"),
+            Err(CustomError::InvalidReference(_))
+        ));
+    }
+
+    #[test]
+    fn parse_match_date_accepts_timestamp_and_rfc3339() {
+        assert_eq!(
+            parse_match_date("100", &Conversion::Timestamp).unwrap(),
+            100 * 1_000_000_000
+        );
+        assert!(parse_match_date("2026-08-15T19:00:00Z", &Conversion::Rfc3339).is_ok());
+    }
+
+    #[test]
+    fn parse_match_date_rejects_garbage() {
+        assert!(parse_match_date("not-a-timestamp", &Conversion::Timestamp).is_err());
+    }
+
+    #[test]
+    fn parse_match_date_rejects_far_future_overflow_instead_of_panicking() {
+        // Seconds-since-epoch for year 9999 overflows when scaled to
+        // nanoseconds in a `u64`; this must be reported as `InvalidDate`
+        // rather than panicking (debug) or silently wrapping (release).
+        let err = parse_match_date("9999-01-01T00:00:00Z", &Conversion::Rfc3339).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidDate(_)));
+    }
+
+    #[test]
+    fn core_add_team_validates_and_checks_stadium() {
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        stadiums.borrow_mut().insert(1, Stadium::default());
+
+        let err = core_add_team(&teams, &stadiums, 1, TeamPayload::default()).unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = TeamPayload {
+            name: "Reds".to_string(),
+            manager: "Klopp".to_string(),
+            stadium: 2,
+        };
+        let err = core_add_team(&teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = TeamPayload {
+            name: "Reds".to_string(),
+            manager: "Klopp".to_string(),
+            stadium: 1,
+        };
+        let team = core_add_team(&teams, &stadiums, 1, payload).unwrap();
+        assert_eq!(teams.borrow().get(&1), Some(team));
+    }
+
+    #[test]
+    fn core_delete_team_blocked_by_coach_and_match() {
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        let coaches = RefCell::new(InMemoryRepo::<u64, Coach>::new());
+        let matches = RefCell::new(InMemoryRepo::<u64, Match>::new());
+        teams.borrow_mut().insert(1, Team::default());
+
+        coaches.borrow_mut().insert(
+            1,
+            Coach {
+                id: 1,
+                name: "Arne".to_string(),
+                team: 1,
+            },
+        );
+        let err = core_delete_team(&teams, &coaches, &matches, 1).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+        coaches.borrow_mut().remove(&1);
+
+        matches.borrow_mut().insert(
+            1,
+            Match {
+                id: 1,
+                home_team: 1,
+                ..Default::default()
+            },
+        );
+        let err = core_delete_team(&teams, &coaches, &matches, 1).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+        matches.borrow_mut().remove(&1);
+
+        assert!(core_delete_team(&teams, &coaches, &matches, 1).is_ok());
+        assert!(teams.borrow().get(&1).is_none());
+    }
+
+    #[test]
+    fn core_update_team_validates_and_checks_stadium() {
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        teams.borrow_mut().insert(
+            1,
+            Team {
+                id: 1,
+                name: "Reds".to_string(),
+                manager: "Klopp".to_string(),
+                stadium: 1,
+            },
+        );
+        stadiums.borrow_mut().insert(1, Stadium::default());
+
+        let payload = TeamPayload {
+            name: "Reds".to_string(),
+            manager: "Slot".to_string(),
+            stadium: 1,
+        };
+        let updated = core_update_team(&teams, &stadiums, 1, payload).unwrap();
+        assert_eq!(updated.manager, "Slot");
+
+        let payload = TeamPayload {
+            name: "Reds".to_string(),
+            manager: "Slot".to_string(),
+            stadium: 2,
+        };
+        let err = core_update_team(&teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+    }
+
+    #[test]
+    fn core_add_coach_validates_and_checks_team() {
+        let coaches = RefCell::new(InMemoryRepo::<u64, Coach>::new());
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        teams.borrow_mut().insert(1, Team::default());
+
+        let err = core_add_coach(&coaches, &teams, 1, CoachPayload::default()).unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = CoachPayload {
+            name: "Arne".to_string(),
+            team: 2,
+        };
+        let err = core_add_coach(&coaches, &teams, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = CoachPayload {
+            name: "Arne".to_string(),
+            team: 1,
+        };
+        let coach = core_add_coach(&coaches, &teams, 1, payload).unwrap();
+        assert_eq!(coaches.borrow().get(&1), Some(coach));
+    }
+
+    #[test]
+    fn core_update_coach_validates_and_checks_team() {
+        let coaches = RefCell::new(InMemoryRepo::<u64, Coach>::new());
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        coaches.borrow_mut().insert(
+            1,
+            Coach {
+                id: 1,
+                name: "Arne".to_string(),
+                team: 1,
+            },
+        );
+        teams.borrow_mut().insert(1, Team::default());
+
+        let payload = CoachPayload {
+            name: "Jurgen".to_string(),
+            team: 1,
+        };
+        let updated = core_update_coach(&coaches, &teams, 1, payload).unwrap();
+        assert_eq!(updated.name, "Jurgen");
+
+        let payload = CoachPayload {
+            name: "Jurgen".to_string(),
+            team: 2,
+        };
+        let err = core_update_coach(&coaches, &teams, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+    }
+
+    #[test]
+    fn core_add_stadium_validates_required_fields() {
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+
+        let err = core_add_stadium(&stadiums, 1, StadiumPayload::default()).unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = StadiumPayload {
+            name: "Anfield".to_string(),
+            location: "Liverpool".to_string(),
+            capacity: 54000,
+        };
+        let stadium = core_add_stadium(&stadiums, 1, payload).unwrap();
+        assert_eq!(stadiums.borrow().get(&1), Some(stadium));
+    }
+
+    #[test]
+    fn core_update_stadium_validates_required_fields() {
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        stadiums.borrow_mut().insert(
+            1,
+            Stadium {
+                id: 1,
+                name: "Anfield".to_string(),
+                location: "Liverpool".to_string(),
+                capacity: 54000,
+            },
+        );
+
+        let err = core_update_stadium(&stadiums, 1, StadiumPayload::default()).unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = StadiumPayload {
+            name: "Anfield".to_string(),
+            location: "Liverpool".to_string(),
+            capacity: 61000,
+        };
+        let updated = core_update_stadium(&stadiums, 1, payload).unwrap();
+        assert_eq!(updated.capacity, 61000);
+    }
+
+    #[test]
+    fn core_delete_stadium_blocked_by_team_and_match() {
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        let matches = RefCell::new(InMemoryRepo::<u64, Match>::new());
+        stadiums.borrow_mut().insert(1, Stadium::default());
+
+        teams.borrow_mut().insert(
+            1,
+            Team {
+                id: 1,
+                stadium: 1,
+                ..Default::default()
+            },
+        );
+        let err = core_delete_stadium(&stadiums, &teams, &matches, 1).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+        teams.borrow_mut().remove(&1);
+
+        matches.borrow_mut().insert(
+            1,
+            Match {
+                id: 1,
+                venue: 1,
+                ..Default::default()
+            },
+        );
+        let err = core_delete_stadium(&stadiums, &teams, &matches, 1).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+        matches.borrow_mut().remove(&1);
+
+        assert!(core_delete_stadium(&stadiums, &teams, &matches, 1).is_ok());
+        assert!(stadiums.borrow().get(&1).is_none());
+    }
+
+    /// The repos `teams_and_stadiums_fixture` returns.
+    type TeamsAndStadiums = (
+        RefCell<InMemoryRepo<u64, Team>>,
+        RefCell<InMemoryRepo<u64, Stadium>>,
+    );
+
+    /// Sets up two teams (ids 1 and 2) and a stadium (id 2) for
+    /// `core_add_match`/`core_update_match` tests that need valid, distinct
+    /// foreign keys to exercise the date-parsing path.
+    fn teams_and_stadiums_fixture() -> TeamsAndStadiums {
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        teams.borrow_mut().insert(1, Team::default());
+        teams.borrow_mut().insert(2, Team::default());
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        stadiums.borrow_mut().insert(2, Stadium::default());
+        (teams, stadiums)
+    }
+
+    #[test]
+    fn core_add_match_validates_fields_references_and_date() {
+        let matches = RefCell::new(InMemoryRepo::<u64, Match>::new());
+        let (teams, stadiums) = teams_and_stadiums_fixture();
+
+        let err = core_add_match(&matches, &teams, &stadiums, 1, MatchPayload::default())
+            .unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 1,
+            venue: 2,
+            match_date: "100".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_add_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = MatchPayload {
+            home_team: 99,
+            away_team: 1,
+            venue: 2,
+            match_date: "100".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_add_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 99,
+            match_date: "100".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_add_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 2,
+            match_date: "not-a-timestamp".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_add_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidDate(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 2,
+            match_date: "100".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let m = core_add_match(&matches, &teams, &stadiums, 1, payload).unwrap();
+        assert_eq!(m.match_date, 100 * 1_000_000_000);
+        assert_eq!(matches.borrow().get(&1), Some(m));
+    }
+
+    #[test]
+    fn core_update_match_validates_fields_references_and_date() {
+        let matches = RefCell::new(InMemoryRepo::<u64, Match>::new());
+        let (teams, stadiums) = teams_and_stadiums_fixture();
+        matches.borrow_mut().insert(
+            1,
+            Match {
+                id: 1,
+                home_team: 1,
+                away_team: 2,
+                venue: 2,
+                match_date: 100 * 1_000_000_000,
+                ..Default::default()
+            },
+        );
+
+        let err = core_update_match(&matches, &teams, &stadiums, 1, MatchPayload::default())
+            .unwrap_err();
+        assert!(matches!(err, CustomError::EmptyFields(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 1,
+            venue: 2,
+            match_date: "200".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_update_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 99,
+            match_date: "200".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let err = core_update_match(&matches, &teams, &stadiums, 1, payload).unwrap_err();
+        assert!(matches!(err, CustomError::InvalidReference(_)));
+
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 2,
+            match_date: "200".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let updated = core_update_match(&matches, &teams, &stadiums, 1, payload).unwrap();
+        assert_eq!(updated.match_date, 200 * 1_000_000_000);
+    }
+
+    #[test]
+    fn compute_standings_only_counts_completed_matches() {
+        let matches = vec![
+            Match {
+                id: 1,
+                home_team: 1,
+                away_team: 2,
+                home_goals: Some(2),
+                away_goals: Some(1),
+                ..Default::default()
+            },
+            Match {
+                id: 2,
+                home_team: 1,
+                away_team: 2,
+                home_goals: None,
+                away_goals: None,
+                ..Default::default()
+            },
+        ];
+
+        let rows = compute_standings(&matches, |id| id.to_string());
+        let total_played: u32 = rows.iter().map(|r| r.played).sum();
+        assert_eq!(total_played, 2);
+    }
+
+    #[test]
+    fn compute_standings_breaks_ties_by_goal_difference_then_goals_for() {
+        let matches = vec![
+            Match {
+                id: 1,
+                home_team: 1,
+                away_team: 2,
+                home_goals: Some(3),
+                away_goals: Some(1),
+                ..Default::default()
+            },
+            Match {
+                id: 2,
+                home_team: 3,
+                away_team: 4,
+                home_goals: Some(2),
+                away_goals: Some(0),
+                ..Default::default()
+            },
+        ];
+
+        let rows = compute_standings(&matches, |id| id.to_string());
+        // Teams 1 and 3 both won with the same +2 goal difference, so the
+        // tie is broken by goals scored: team 1 (3) ranks above team 3 (2).
+        let team1 = rows.iter().find(|r| r.team == "1").unwrap();
+        let team3 = rows.iter().find(|r| r.team == "3").unwrap();
+        assert_eq!(team1.points, team3.points);
+        let team1_pos = rows.iter().position(|r| r.team == "1").unwrap();
+        let team3_pos = rows.iter().position(|r| r.team == "3").unwrap();
+        assert!(team1_pos < team3_pos, "team 1 has more goals for, should rank first");
+    }
+
+    #[test]
+    fn next_seq_from_advances_on_time_and_on_collision() {
+        // IC time moved forward since the last op: use it directly.
+        assert_eq!(next_seq_from(10, 20), 20);
+        // IC time stalled or went backwards relative to the last op (two
+        // operations in the same nanosecond): fall back to last + 1 so
+        // sequence numbers stay strictly increasing.
+        assert_eq!(next_seq_from(10, 10), 11);
+        assert_eq!(next_seq_from(10, 5), 11);
+    }
+
+    #[test]
+    fn checkpoint_written_at_cadence_boundary() {
+        let log = RefCell::new(InMemoryRepo::<u64, Operation>::new());
+        let checkpoints = RefCell::new(InMemoryRepo::<u64, Snapshot>::new());
+        let teams = RefCell::new(InMemoryRepo::<u64, Team>::new());
+        let coaches = RefCell::new(InMemoryRepo::<u64, Coach>::new());
+        let stadiums = RefCell::new(InMemoryRepo::<u64, Stadium>::new());
+        let matches = RefCell::new(InMemoryRepo::<u64, Match>::new());
+        let repos = EntityRepos {
+            teams: &teams,
+            coaches: &coaches,
+            stadiums: &stadiums,
+            matches: &matches,
+        };
+
+        for seq in 1..KEEP_STATE_EVERY {
+            let team = Team {
+                id: seq,
+                ..Default::default()
+            };
+            teams.borrow_mut().insert(seq, team.clone());
+            core_append_operation(&log, &checkpoints, &repos, seq, Operation::AddTeam(team));
+        }
+        assert!(
+            checkpoints.borrow().is_empty(),
+            "no checkpoint should be taken before the cadence boundary"
+        );
+
+        let seq = KEEP_STATE_EVERY;
+        let team = Team {
+            id: seq,
+            ..Default::default()
+        };
+        teams.borrow_mut().insert(seq, team.clone());
+        core_append_operation(&log, &checkpoints, &repos, seq, Operation::AddTeam(team));
+
+        let snapshot = checkpoints
+            .borrow()
+            .get(&KEEP_STATE_EVERY)
+            .expect("a checkpoint should be taken exactly at the boundary");
+        assert_eq!(snapshot.teams.len(), KEEP_STATE_EVERY as usize);
+    }
+
+    #[test]
+    fn rebuild_state_at_replays_tail_since_checkpoint() {
+        let checkpoints = RefCell::new(InMemoryRepo::<u64, Snapshot>::new());
+        let log = RefCell::new(InMemoryRepo::<u64, Operation>::new());
+
+        let checkpointed_team = Team {
+            id: 1,
+            name: "Reds".to_string(),
+            ..Default::default()
+        };
+        checkpoints.borrow_mut().insert(
+            10,
+            Snapshot {
+                teams: vec![checkpointed_team.clone()],
+                ..Default::default()
+            },
+        );
+
+        let added_coach = Coach {
+            id: 1,
+            name: "Arne".to_string(),
+            team: 1,
+        };
+        log.borrow_mut()
+            .insert(11, Operation::AddCoach(added_coach.clone()));
+        log.borrow_mut().insert(
+            12,
+            Operation::UpdateTeam {
+                id: 1,
+                team: Team {
+                    name: "Blues".to_string(),
+                    ..checkpointed_team
+                },
+            },
+        );
+
+        let state = core_rebuild_state_at(&checkpoints, &log, 12);
+        assert_eq!(state.teams.len(), 1);
+        assert_eq!(state.teams[0].name, "Blues");
+        assert_eq!(state.coaches, vec![added_coach]);
+    }
+
+    #[test]
+    fn rollback_then_append_does_not_replay_discarded_tail() {
+        let checkpoints = RefCell::new(InMemoryRepo::<u64, Snapshot>::new());
+        let log = RefCell::new(InMemoryRepo::<u64, Operation>::new());
+
+        let team_a = Team {
+            id: 1,
+            name: "A".to_string(),
+            ..Default::default()
+        };
+        let team_b = Team {
+            id: 1,
+            name: "B".to_string(),
+            ..Default::default()
+        };
+        log.borrow_mut().insert(1, Operation::AddTeam(team_a.clone()));
+        log.borrow_mut().insert(
+            2,
+            Operation::UpdateTeam {
+                id: 1,
+                team: team_b,
+            },
+        );
+
+        // Roll back to seq 1, before the rename to "B".
+        let rolled_back = core_rebuild_state_at(&checkpoints, &log, 1);
+        assert_eq!(rolled_back.teams, vec![team_a.clone()]);
+        core_discard_log_after(&log, &checkpoints, 1);
+        assert_eq!(log.borrow().len(), 1);
+
+        // A later operation reuses the discarded seq 2: it must not
+        // resurrect the rename that used to live there.
+        let seq = next_seq_from(1, 1);
+        assert_eq!(seq, 2);
+        let team_c = Team {
+            id: 2,
+            name: "C".to_string(),
+            ..Default::default()
+        };
+        log.borrow_mut().insert(seq, Operation::AddTeam(team_c.clone()));
+
+        let rebuilt = core_rebuild_state_at(&checkpoints, &log, seq);
+        assert_eq!(rebuilt.teams, vec![team_a, team_c]);
+    }
+
+    /// Inserts `m` into both `MATCH_STORAGE` and the `MATCH_BY_DATE` index,
+    /// the way `add_match` does, so index tests don't have to go through the
+    /// full `#[ic_cdk::update]` handler.
+    fn insert_match(m: Match) {
+        MATCH_STORAGE.with(|s| s.borrow_mut().insert(m.id, m.clone()));
+        index_match(&m);
+    }
+
+    #[test]
+    fn list_matches_between_is_inclusive_and_date_ordered() {
+        insert_match(Match {
+            id: 1,
+            match_date: 100,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 2,
+            match_date: 200,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 3,
+            match_date: 300,
+            ..Default::default()
+        });
+
+        let ids: Vec<u64> = list_matches_between(100, 200).iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn list_matches_for_team_matches_home_or_away() {
+        insert_match(Match {
+            id: 1,
+            home_team: 1,
+            away_team: 2,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 2,
+            home_team: 2,
+            away_team: 1,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 3,
+            home_team: 3,
+            away_team: 4,
+            ..Default::default()
+        });
+
+        let ids: Vec<u64> = list_matches_for_team(1).iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn list_upcoming_matches_respects_now_and_limit() {
+        insert_match(Match {
+            id: 1,
+            match_date: 100,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 2,
+            match_date: 200,
+            ..Default::default()
+        });
+        insert_match(Match {
+            id: 3,
+            match_date: 300,
+            ..Default::default()
+        });
+
+        let ids: Vec<u64> = list_upcoming_matches(150, 1).iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+
+    /// Exercises the same index-maintenance logic as the `update_match`/
+    /// `delete_match` handlers (core update/delete plus `index_match`/
+    /// `unindex_match`), without going through `append_operation`'s
+    /// IC-time-derived sequence numbering.
+    #[test]
+    fn update_and_delete_match_keep_the_date_index_in_sync() {
+        TEAM_STORAGE.with(|s| s.borrow_mut().insert(1, Team::default()));
+        TEAM_STORAGE.with(|s| s.borrow_mut().insert(2, Team::default()));
+        STADIUM_STORAGE.with(|s| s.borrow_mut().insert(2, Stadium::default()));
+        insert_match(Match {
+            id: 1,
+            home_team: 1,
+            away_team: 2,
+            venue: 2,
+            match_date: 100 * 1_000_000_000,
+            ..Default::default()
+        });
+
+        let old_date = MATCH_STORAGE.with(|s| s.borrow().get(&1)).unwrap().match_date;
+        let payload = MatchPayload {
+            home_team: 1,
+            away_team: 2,
+            venue: 2,
+            match_date: "200".to_string(),
+            date_conversion: Conversion::Timestamp,
+        };
+        let updated = MATCH_STORAGE.with(|storage| {
+            TEAM_STORAGE.with(|teams| {
+                STADIUM_STORAGE
+                    .with(|stadiums| core_update_match(storage, teams, stadiums, 1, payload))
+            })
+        })
+        .unwrap();
+        unindex_match(old_date, 1);
+        index_match(&updated);
+
+        let old_key_present = MATCH_BY_DATE.with(|idx| idx.borrow().get(&(old_date, 1)));
+        assert!(old_key_present.is_none(), "stale (date, id) entry should be removed");
+        let new_key_present =
+            MATCH_BY_DATE.with(|idx| idx.borrow().get(&(updated.match_date, 1)));
+        assert!(new_key_present.is_some(), "new (date, id) entry should be present");
+        assert_eq!(
+            list_matches_between(updated.match_date, updated.match_date)
+                .iter()
+                .map(|m| m.id)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        let removed = MATCH_STORAGE.with(|storage| {
+            remove_existing(storage, 1, || CustomError::NotFound("Match with ID 1 not found".to_string()))
+        })
+        .unwrap();
+        unindex_match(removed.match_date, 1);
+
+        let removed_key_present =
+            MATCH_BY_DATE.with(|idx| idx.borrow().get(&(removed.match_date, 1)));
+        assert!(
+            removed_key_present.is_none(),
+            "index entry should be removed on delete"
+        );
+    }
+
+    #[test]
+    fn discard_log_after_removes_stale_ops_and_checkpoints() {
+        let log = RefCell::new(InMemoryRepo::<u64, Operation>::new());
+        let checkpoints = RefCell::new(InMemoryRepo::<u64, Snapshot>::new());
+        log.borrow_mut().insert(1, Operation::AddTeam(Team::default()));
+        log.borrow_mut().insert(2, Operation::AddTeam(Team::default()));
+        log.borrow_mut().insert(3, Operation::AddTeam(Team::default()));
+        checkpoints.borrow_mut().insert(2, Snapshot::default());
+
+        core_discard_log_after(&log, &checkpoints, 1);
+
+        assert_eq!(
+            log.borrow().iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert!(checkpoints.borrow().is_empty());
+    }
+}
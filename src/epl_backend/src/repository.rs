@@ -0,0 +1,194 @@
+//! Pluggable storage backend for the entity maps in `lib.rs`.
+//!
+//! CRUD handlers operate against the `Repository` trait instead of talking
+//! to `StableBTreeMap` directly, so the same logic can run under `cargo
+//! test` against `InMemoryRepo` without the IC runtime, and on-canister
+//! against `StableRepo`.
+
+use candid::{Decode, Encode};
+use ic_stable_structures::{BoundedStorable, Memory, StableBTreeMap, Storable};
+use std::borrow::Cow;
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+/// A key-value store keyed by `K`, independent of the backing storage.
+pub trait Repository<K, V> {
+    fn get(&self, key: &K) -> Option<V>;
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn range<R: RangeBounds<K>>(&self, range: R) -> Box<dyn Iterator<Item = (K, V)> + '_>;
+    fn len(&self) -> u64;
+
+    // Only exercised by `#[cfg(test)]` call sites today; kept on the trait
+    // (rather than test-gated) since it's a natural companion to `len` that
+    // any `Repository` consumer, including future non-test ones, expects.
+    #[allow(dead_code)]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        self.range(..)
+    }
+}
+
+/// Supplies the `BoundedStorable::MAX_SIZE` for a candid-encodable value, so
+/// entities only need to declare a size instead of hand-writing
+/// `Storable`/`BoundedStorable` boilerplate.
+pub trait MaxEncodedSize {
+    const MAX_SIZE: u32;
+}
+
+/// Wraps any candid-encodable `T` so `Storable`/`BoundedStorable` can be
+/// implemented for it once, generically, instead of once per entity type
+/// (candid's `Encode!`/`Decode!` can't implement a foreign trait for a bare
+/// generic `T` directly because of the orphan rule).
+pub struct Encoded<T>(pub T);
+
+impl<T> Storable for Encoded<T>
+where
+    T: candid::CandidType + for<'de> serde::Deserialize<'de>,
+{
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(&self.0).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Encoded(Decode!(bytes.as_ref(), T).unwrap())
+    }
+}
+
+impl<T> BoundedStorable for Encoded<T>
+where
+    T: candid::CandidType + for<'de> serde::Deserialize<'de> + MaxEncodedSize,
+{
+    const MAX_SIZE: u32 = T::MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// `Repository` backed by a `StableBTreeMap`, for use on-canister.
+pub struct StableRepo<K, V, M>
+where
+    K: Storable + Ord + Clone + BoundedStorable,
+    V: candid::CandidType + for<'de> serde::Deserialize<'de> + MaxEncodedSize + Clone,
+    M: Memory,
+{
+    map: StableBTreeMap<K, Encoded<V>, M>,
+}
+
+impl<K, V, M> StableRepo<K, V, M>
+where
+    K: Storable + Ord + Clone + BoundedStorable,
+    V: candid::CandidType + for<'de> serde::Deserialize<'de> + MaxEncodedSize + Clone,
+    M: Memory,
+{
+    pub fn new(memory: M) -> Self {
+        Self {
+            map: StableBTreeMap::init(memory),
+        }
+    }
+}
+
+impl<K, V, M> Repository<K, V> for StableRepo<K, V, M>
+where
+    K: Storable + Ord + Clone + BoundedStorable,
+    V: candid::CandidType + for<'de> serde::Deserialize<'de> + MaxEncodedSize + Clone,
+    M: Memory,
+{
+    fn get(&self, key: &K) -> Option<V> {
+        self.map.get(key).map(|Encoded(v)| v)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, Encoded(value)).map(|Encoded(v)| v)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key).map(|Encoded(v)| v)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.map.range(range).map(|(k, Encoded(v))| (k, v)))
+    }
+
+    fn len(&self) -> u64 {
+        self.map.len()
+    }
+}
+
+/// `Repository` backed by a plain `std::collections::BTreeMap`, for use in
+/// unit tests run with `cargo test` off the IC runtime.
+#[cfg(test)]
+#[derive(Default)]
+pub struct InMemoryRepo<K, V> {
+    map: BTreeMap<K, V>,
+}
+
+#[cfg(test)]
+impl<K: Ord, V> InMemoryRepo<K, V> {
+    pub fn new() -> Self {
+        Self { map: BTreeMap::new() }
+    }
+}
+
+#[cfg(test)]
+impl<K, V> Repository<K, V> for InMemoryRepo<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    fn get(&self, key: &K) -> Option<V> {
+        self.map.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.map.range(range).map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    fn len(&self) -> u64 {
+        self.map.len() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_insert_remove_round_trip() {
+        let mut repo: InMemoryRepo<u64, &str> = InMemoryRepo::new();
+        assert!(repo.is_empty());
+
+        assert_eq!(repo.insert(1, "a"), None);
+        assert_eq!(repo.insert(2, "b"), None);
+        assert_eq!(repo.get(&1), Some("a"));
+        assert_eq!(repo.len(), 2);
+
+        assert_eq!(repo.insert(1, "a2"), Some("a"));
+        assert_eq!(repo.get(&1), Some("a2"));
+
+        assert_eq!(repo.remove(&1), Some("a2"));
+        assert_eq!(repo.get(&1), None);
+        assert_eq!(repo.len(), 1);
+    }
+
+    #[test]
+    fn range_and_iter_are_key_ordered() {
+        let mut repo: InMemoryRepo<u64, u64> = InMemoryRepo::new();
+        for k in [3, 1, 2] {
+            repo.insert(k, k * 10);
+        }
+
+        assert_eq!(repo.iter().collect::<Vec<_>>(), vec![(1, 10), (2, 20), (3, 30)]);
+        assert_eq!(repo.range(2..).collect::<Vec<_>>(), vec![(2, 20), (3, 30)]);
+    }
+}